@@ -7,7 +7,7 @@ type CowPath<'a> = borrow::Cow<'a, path::Path>;
 type CowStr<'a> = borrow::Cow<'a, str>;
 
 /// The error code associated to this diagnostic.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct DiagnosticCode<'a> {
     /// The code itself.
     #[serde(borrow)]
@@ -20,8 +20,20 @@ pub struct DiagnosticCode<'a> {
     __do_not_match_exhaustively: (),
 }
 
+impl<'a> DiagnosticCode<'a> {
+    /// Detach `self` from the buffer it was parsed from by cloning every
+    /// borrowed field into an owned one.
+    pub fn into_owned(self) -> DiagnosticCode<'static> {
+        DiagnosticCode {
+            code: owned_str(self.code),
+            explanation: self.explanation.map(owned_str),
+            __do_not_match_exhaustively: (),
+        }
+    }
+}
+
 /// A line of code associated with the Diagnostic
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct DiagnosticSpanLine<'a> {
     /// The line of code associated with the error
     #[serde(borrow)]
@@ -35,8 +47,21 @@ pub struct DiagnosticSpanLine<'a> {
     __do_not_match_exhaustively: (),
 }
 
+impl<'a> DiagnosticSpanLine<'a> {
+    /// Detach `self` from the buffer it was parsed from by cloning every
+    /// borrowed field into an owned one.
+    pub fn into_owned(self) -> DiagnosticSpanLine<'static> {
+        DiagnosticSpanLine {
+            text: owned_str(self.text),
+            highlight_start: self.highlight_start,
+            highlight_end: self.highlight_end,
+            __do_not_match_exhaustively: (),
+        }
+    }
+}
+
 /// Macro expansion information associated with a diagnostic.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct DiagnosticSpanMacroExpansion<'a> {
     /// span where macro was applied to generate this code; note that
     /// this may itself derive from a macro (if
@@ -57,8 +82,21 @@ pub struct DiagnosticSpanMacroExpansion<'a> {
     __do_not_match_exhaustively: (),
 }
 
+impl<'a> DiagnosticSpanMacroExpansion<'a> {
+    /// Detach `self` from the buffer it was parsed from by cloning every
+    /// borrowed field into an owned one.
+    pub fn into_owned(self) -> DiagnosticSpanMacroExpansion<'static> {
+        DiagnosticSpanMacroExpansion {
+            span: self.span.into_owned(),
+            macro_decl_name: owned_str(self.macro_decl_name),
+            def_site_span: self.def_site_span.map(DiagnosticSpan::into_owned),
+            __do_not_match_exhaustively: (),
+        }
+    }
+}
+
 /// A section of the source code associated with a Diagnostic
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct DiagnosticSpan<'a> {
     /// The file name this diagnostic comes from.
     #[serde(borrow)]
@@ -98,8 +136,93 @@ pub struct DiagnosticSpan<'a> {
     __do_not_match_exhaustively: (),
 }
 
+impl<'a> DiagnosticSpan<'a> {
+    /// Detach `self` from the buffer it was parsed from by cloning every
+    /// borrowed field, including the `expansion` chain, into an owned one.
+    pub fn into_owned(self) -> DiagnosticSpan<'static> {
+        DiagnosticSpan {
+            file_name: owned_path(self.file_name),
+            byte_start: self.byte_start,
+            byte_end: self.byte_end,
+            line_start: self.line_start,
+            line_end: self.line_end,
+            column_start: self.column_start,
+            column_end: self.column_end,
+            is_primary: self.is_primary,
+            text: self
+                .text
+                .into_iter()
+                .map(DiagnosticSpanLine::into_owned)
+                .collect(),
+            label: self.label.map(owned_str),
+            suggested_replacement: self.suggested_replacement.map(owned_str),
+            suggestion_applicability: self.suggestion_applicability,
+            expansion: self
+                .expansion
+                .map(|expansion| Box::new(expansion.into_owned())),
+            __do_not_match_exhaustively: (),
+        }
+    }
+
+    /// The 1-based line/column range covered by this span, as rustc reports
+    /// it.
+    pub fn range(&self) -> Range {
+        Range {
+            start: Position {
+                line: self.line_start,
+                column: self.column_start,
+            },
+            end: Position {
+                line: self.line_end,
+                column: self.column_end,
+            },
+        }
+    }
+
+    /// Like [`range`](Self::range), but converted to 0-based line/column,
+    /// the convention LSP consumers expect.
+    pub fn range_zero_based(&self) -> Range {
+        let Range { start, end } = self.range();
+        Range {
+            start: start.zero_based(),
+            end: end.zero_based(),
+        }
+    }
+}
+
+/// A line/column position within a source file, following the rls-span
+/// convention.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Position {
+    /// The line, starting from either 1 or 0; see the method that produced
+    /// this `Position`.
+    pub line: usize,
+    /// The column, starting from either 1 or 0; see the method that produced
+    /// this `Position`.
+    pub column: usize,
+}
+
+impl Position {
+    fn zero_based(self) -> Self {
+        Position {
+            line: self.line.saturating_sub(1),
+            column: self.column.saturating_sub(1),
+        }
+    }
+}
+
+/// A half-open `[start, end)` range of `Position`s, as used by rls-span and
+/// LSP-style tooling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Range {
+    /// The start of the range, inclusive.
+    pub start: Position,
+    /// The end of the range, exclusive.
+    pub end: Position,
+}
+
 /// Whether a suggestion can be safely applied.
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Applicability {
     /// The suggested replacement can be applied automatically safely
     MachineApplicable,
@@ -117,7 +240,7 @@ pub enum Applicability {
 }
 
 /// A diagnostic message generated by rustc
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Diagnostic<'a> {
     /// The error message of this diagnostic.
     #[serde(borrow)]
@@ -141,8 +264,78 @@ pub struct Diagnostic<'a> {
     __do_not_match_exhaustively: (),
 }
 
+impl<'a> Diagnostic<'a> {
+    /// Detach `self` from the buffer it was parsed from by recursively
+    /// cloning every `Cow::Borrowed` field, including `spans` and `children`,
+    /// into an owned one.
+    ///
+    /// This lets callers collect a `Vec<Diagnostic<'static>>` across many
+    /// build-output lines instead of being tied to the lifetime of the line
+    /// they were read from.
+    pub fn into_owned(self) -> Diagnostic<'static> {
+        Diagnostic {
+            message: owned_str(self.message),
+            code: self.code.map(DiagnosticCode::into_owned),
+            level: self.level,
+            spans: self
+                .spans
+                .into_iter()
+                .map(DiagnosticSpan::into_owned)
+                .collect(),
+            children: self
+                .children
+                .into_iter()
+                .map(Diagnostic::into_owned)
+                .collect(),
+            rendered: self.rendered.map(owned_str),
+            __do_not_match_exhaustively: (),
+        }
+    }
+
+    /// Iterate over this diagnostic's primary spans, i.e. those with
+    /// `is_primary == true`, so tools can jump straight to the error location
+    /// without filtering the span list themselves.
+    pub fn primary_spans(&self) -> impl Iterator<Item = &DiagnosticSpan<'a>> {
+        self.spans.iter().filter(|span| span.is_primary)
+    }
+}
+
+/// Returns each distinct diagnostic from `diagnostics`, in order, dropping
+/// later duplicates.
+///
+/// A diagnostic is considered a duplicate of an earlier one if it has the
+/// same `message`, `code`, and `spans`; `cargo build`/`cargo test` commonly
+/// emit the same warning once per target it touches, and this lets callers
+/// stop reporting it more than once.
+pub fn dedup_diagnostics<'a, I>(diagnostics: I) -> Vec<Diagnostic<'a>>
+where
+    I: IntoIterator<Item = Diagnostic<'a>>,
+{
+    let mut seen = std::collections::HashSet::new();
+    let mut deduped = Vec::new();
+    for diagnostic in diagnostics {
+        let key = (
+            diagnostic.message.clone(),
+            diagnostic.code.as_ref().map(|code| code.code.clone()),
+            diagnostic.spans.clone(),
+        );
+        if seen.insert(key) {
+            deduped.push(diagnostic);
+        }
+    }
+    deduped
+}
+
+fn owned_str(cow: CowStr<'_>) -> CowStr<'static> {
+    borrow::Cow::Owned(cow.into_owned())
+}
+
+fn owned_path(cow: CowPath<'_>) -> CowPath<'static> {
+    borrow::Cow::Owned(cow.into_owned())
+}
+
 /// The diagnostic level
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum DiagnosticLevel {
     /// Internal compiler error
@@ -161,3 +354,69 @@ pub enum DiagnosticLevel {
     #[serde(other)]
     Unknown,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JSON: &str = r#"{
+        "message": "unused variable: `x`",
+        "code": {"code": "unused_variables", "explanation": null},
+        "level": "warning",
+        "spans": [{
+            "file_name": "src/lib.rs",
+            "byte_start": 10,
+            "byte_end": 11,
+            "line_start": 2,
+            "line_end": 2,
+            "column_start": 5,
+            "column_end": 6,
+            "is_primary": true,
+            "text": [{"text": "let x = 1;", "highlight_start": 5, "highlight_end": 6}],
+            "label": "unused variable",
+            "suggested_replacement": null,
+            "suggestion_applicability": null,
+            "expansion": null
+        }],
+        "children": [],
+        "rendered": null
+    }"#;
+
+    #[test]
+    fn into_owned_outlives_the_source_buffer() {
+        let owned: Diagnostic<'static> = {
+            let buffer = JSON.to_owned();
+            let diagnostic: Diagnostic<'_> = serde_json::from_str(&buffer).unwrap();
+            diagnostic.into_owned()
+        };
+        assert_eq!(owned.message, "unused variable: `x`");
+        assert_eq!(owned.spans[0].file_name.display().to_string(), "src/lib.rs");
+    }
+
+    #[test]
+    fn primary_spans_and_range_conversion() {
+        let diagnostic: Diagnostic<'_> = serde_json::from_str(JSON).unwrap();
+        let primary: Vec<_> = diagnostic.primary_spans().collect();
+        assert_eq!(primary.len(), 1);
+
+        let range = primary[0].range();
+        assert_eq!(range.start, Position { line: 2, column: 5 });
+        assert_eq!(range.end, Position { line: 2, column: 6 });
+
+        let zero_based = primary[0].range_zero_based();
+        assert_eq!(zero_based.start, Position { line: 1, column: 4 });
+        assert_eq!(zero_based.end, Position { line: 1, column: 5 });
+    }
+
+    #[test]
+    fn dedup_diagnostics_keys_on_message_code_and_spans() {
+        let a: Diagnostic<'_> = serde_json::from_str(JSON).unwrap();
+        let b: Diagnostic<'_> = serde_json::from_str(JSON).unwrap();
+        let mut c: Diagnostic<'_> = serde_json::from_str(JSON).unwrap();
+        c.spans[0].byte_start = 0;
+
+        let deduped = dedup_diagnostics(vec![a, b, c]);
+
+        assert_eq!(deduped.len(), 2);
+    }
+}
@@ -0,0 +1,354 @@
+//! Apply rustc's `suggested_replacement`s back onto source files, the way
+//! `rustfix` does.
+
+use std::collections::HashMap;
+use std::io;
+use std::path;
+
+use super::diagnostic::{Applicability, Diagnostic, DiagnosticSpan};
+
+/// A single replacement within a file, expressed as a half-open byte range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    /// The byte offset where the replaced text starts.
+    pub byte_start: u32,
+    /// The byte offset where the replaced text ends.
+    pub byte_end: u32,
+    /// The text to splice in over `byte_start..byte_end`.
+    pub replacement: String,
+}
+
+/// An error applying suggested edits to source files.
+#[derive(Debug)]
+pub enum FixError {
+    /// Two suggested edits in the same file overlap and can't be spliced
+    /// atomically.
+    OverlappingEdits {
+        /// The file the overlapping edits belong to.
+        file_name: path::PathBuf,
+        /// The byte offset where the overlap was detected.
+        byte_start: u32,
+    },
+    /// Reading a file referenced by a diagnostic failed.
+    Io {
+        /// The file that couldn't be read.
+        file_name: path::PathBuf,
+        /// The underlying error.
+        source: io::Error,
+    },
+}
+
+impl std::fmt::Display for FixError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FixError::OverlappingEdits {
+                file_name,
+                byte_start,
+            } => write!(
+                f,
+                "overlapping suggested edits in `{}` at byte {}",
+                file_name.display(),
+                byte_start
+            ),
+            FixError::Io { file_name, source } => {
+                write!(f, "failed reading `{}`: {}", file_name.display(), source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FixError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FixError::OverlappingEdits { .. } => None,
+            FixError::Io { source, .. } => Some(source),
+        }
+    }
+}
+
+/// Walk `diagnostics` and every nested `children` diagnostic, collecting each
+/// span whose `suggestion_applicability` meets `threshold`, grouped by
+/// `file_name`.
+///
+/// `threshold` is the least-safe `Applicability` the caller is willing to
+/// accept; pass `Applicability::MachineApplicable` to only collect edits
+/// rustc considers safe to apply unattended.
+pub fn collect_edits<'a>(
+    diagnostics: &[Diagnostic<'a>],
+    threshold: Applicability,
+) -> HashMap<path::PathBuf, Vec<Edit>> {
+    let mut edits = HashMap::new();
+    for diagnostic in diagnostics {
+        collect_from_diagnostic(diagnostic, threshold, &mut edits);
+    }
+    edits
+}
+
+fn collect_from_diagnostic<'a>(
+    diagnostic: &Diagnostic<'a>,
+    threshold: Applicability,
+    edits: &mut HashMap<path::PathBuf, Vec<Edit>>,
+) {
+    for span in &diagnostic.spans {
+        collect_from_span(span, threshold, edits);
+    }
+    for child in &diagnostic.children {
+        collect_from_diagnostic(child, threshold, edits);
+    }
+}
+
+fn collect_from_span<'a>(
+    span: &DiagnosticSpan<'a>,
+    threshold: Applicability,
+    edits: &mut HashMap<path::PathBuf, Vec<Edit>>,
+) {
+    // Deliberately does not recurse into `span.expansion`: an expansion span
+    // points at the macro invocation or definition site, not at the span the
+    // suggestion is meant to replace, so splicing it in would rewrite the
+    // wrong bytes.
+    if let (Some(replacement), Some(applicability)) =
+        (&span.suggested_replacement, span.suggestion_applicability)
+    {
+        if meets_threshold(applicability, threshold) {
+            edits
+                .entry(span.file_name.to_path_buf())
+                .or_default()
+                .push(Edit {
+                    byte_start: span.byte_start,
+                    byte_end: span.byte_end,
+                    replacement: replacement.clone().into_owned(),
+                });
+        }
+    }
+}
+
+fn meets_threshold(applicability: Applicability, threshold: Applicability) -> bool {
+    applicability_rank(applicability) <= applicability_rank(threshold)
+}
+
+fn applicability_rank(applicability: Applicability) -> u8 {
+    match applicability {
+        Applicability::MachineApplicable => 0,
+        Applicability::HasPlaceholders => 1,
+        Applicability::MaybeIncorrect => 2,
+        Applicability::Unspecified => 3,
+        Applicability::Unknown => 3,
+    }
+}
+
+/// Apply `edits` onto `source`, returning the patched contents.
+///
+/// Edits are sorted by `byte_start` and spliced from the end of the file
+/// towards the start so earlier offsets stay valid. Byte-identical duplicate
+/// edits are collapsed first, since multi-target builds (lib + tests, several
+/// bins) routinely emit the same suggestion more than once. If two distinct
+/// edits still overlap, the whole batch is rejected since rustc's byte ranges
+/// must be spliced atomically.
+pub fn apply_edits(
+    file_name: &path::Path,
+    source: &str,
+    edits: &mut Vec<Edit>,
+) -> Result<String, FixError> {
+    edits.sort_by_key(|edit| (edit.byte_start, edit.byte_end));
+    edits.dedup();
+    for window in edits.windows(2) {
+        if window[1].byte_start < window[0].byte_end {
+            return Err(FixError::OverlappingEdits {
+                file_name: file_name.to_path_buf(),
+                byte_start: window[1].byte_start,
+            });
+        }
+    }
+
+    let mut buffer = source.to_owned();
+    for edit in edits.iter().rev() {
+        buffer.replace_range(edit.byte_start as usize..edit.byte_end as usize, &edit.replacement);
+    }
+    Ok(buffer)
+}
+
+/// Apply every suggestion in `diagnostics` meeting `threshold`, reading each
+/// affected file with `read_file`.
+///
+/// Returns the rewritten contents of every file that had at least one edit
+/// applied; files without suggestions are left untouched and omitted.
+pub fn apply_suggestions<'a>(
+    diagnostics: &[Diagnostic<'a>],
+    threshold: Applicability,
+    mut read_file: impl FnMut(&path::Path) -> io::Result<String>,
+) -> Result<HashMap<path::PathBuf, String>, FixError> {
+    let mut patched = HashMap::new();
+    for (file_name, mut edits) in collect_edits(diagnostics, threshold) {
+        let source = read_file(&file_name).map_err(|source| FixError::Io {
+            file_name: file_name.clone(),
+            source,
+        })?;
+        let contents = apply_edits(&file_name, &source, &mut edits)?;
+        patched.insert(file_name, contents);
+    }
+    Ok(patched)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span_diagnostic(span_json: &str) -> Diagnostic<'static> {
+        let json = format!(
+            r#"{{"message":"msg","code":null,"level":"error","spans":[{}],"children":[],"rendered":null}}"#,
+            span_json
+        );
+        let diagnostic: Diagnostic<'_> = serde_json::from_str(&json).unwrap();
+        diagnostic.into_owned()
+    }
+
+    #[test]
+    fn apply_edits_splices_non_overlapping_right_to_left() {
+        let mut edits = vec![
+            Edit {
+                byte_start: 4,
+                byte_end: 7,
+                replacement: "bar".to_owned(),
+            },
+            Edit {
+                byte_start: 0,
+                byte_end: 3,
+                replacement: "baz".to_owned(),
+            },
+        ];
+        let patched =
+            apply_edits(path::Path::new("src/lib.rs"), "foo bar baz", &mut edits).unwrap();
+        assert_eq!(patched, "baz bar baz");
+    }
+
+    #[test]
+    fn apply_edits_rejects_overlap() {
+        let mut edits = vec![
+            Edit {
+                byte_start: 0,
+                byte_end: 5,
+                replacement: "a".to_owned(),
+            },
+            Edit {
+                byte_start: 3,
+                byte_end: 8,
+                replacement: "b".to_owned(),
+            },
+        ];
+        let err =
+            apply_edits(path::Path::new("src/lib.rs"), "0123456789", &mut edits).unwrap_err();
+        assert!(matches!(err, FixError::OverlappingEdits { .. }));
+    }
+
+    #[test]
+    fn apply_edits_collapses_byte_identical_duplicates() {
+        // Multi-target builds (lib + tests, several bins) emit the same
+        // suggestion once per target; that must not look like an overlap.
+        let mut edits = vec![
+            Edit {
+                byte_start: 0,
+                byte_end: 3,
+                replacement: "bar".to_owned(),
+            },
+            Edit {
+                byte_start: 0,
+                byte_end: 3,
+                replacement: "bar".to_owned(),
+            },
+        ];
+        let patched = apply_edits(path::Path::new("src/lib.rs"), "foo", &mut edits).unwrap();
+        assert_eq!(patched, "bar");
+    }
+
+    #[test]
+    fn applicability_threshold_ranks() {
+        assert!(meets_threshold(
+            Applicability::MachineApplicable,
+            Applicability::MachineApplicable
+        ));
+        assert!(!meets_threshold(
+            Applicability::MaybeIncorrect,
+            Applicability::MachineApplicable
+        ));
+        assert!(meets_threshold(
+            Applicability::HasPlaceholders,
+            Applicability::MaybeIncorrect
+        ));
+    }
+
+    #[test]
+    fn collect_edits_ignores_expansion_spans() {
+        let diagnostic = span_diagnostic(
+            r#"{
+                "file_name": "src/lib.rs",
+                "byte_start": 0,
+                "byte_end": 3,
+                "line_start": 1,
+                "line_end": 1,
+                "column_start": 1,
+                "column_end": 4,
+                "is_primary": true,
+                "text": [],
+                "label": null,
+                "suggested_replacement": null,
+                "suggestion_applicability": null,
+                "expansion": {
+                    "span": {
+                        "file_name": "src/lib.rs",
+                        "byte_start": 10,
+                        "byte_end": 13,
+                        "line_start": 2,
+                        "line_end": 2,
+                        "column_start": 1,
+                        "column_end": 4,
+                        "is_primary": false,
+                        "text": [],
+                        "label": null,
+                        "suggested_replacement": "nope",
+                        "suggestion_applicability": "MachineApplicable",
+                        "expansion": null
+                    },
+                    "macro_decl_name": "foo!",
+                    "def_site_span": null
+                }
+            }"#,
+        );
+        let edits = collect_edits(
+            std::slice::from_ref(&diagnostic),
+            Applicability::MachineApplicable,
+        );
+        assert!(edits.is_empty());
+    }
+
+    #[test]
+    fn collect_edits_filters_by_threshold() {
+        let diagnostic = span_diagnostic(
+            r#"{
+                "file_name": "src/lib.rs",
+                "byte_start": 0,
+                "byte_end": 3,
+                "line_start": 1,
+                "line_end": 1,
+                "column_start": 1,
+                "column_end": 4,
+                "is_primary": true,
+                "text": [],
+                "label": null,
+                "suggested_replacement": "bar",
+                "suggestion_applicability": "MaybeIncorrect",
+                "expansion": null
+            }"#,
+        );
+        let diagnostics = std::slice::from_ref(&diagnostic);
+        assert!(collect_edits(diagnostics, Applicability::MachineApplicable).is_empty());
+        let edits = collect_edits(diagnostics, Applicability::MaybeIncorrect);
+        assert_eq!(
+            edits.get(path::Path::new("src/lib.rs")).unwrap(),
+            &vec![Edit {
+                byte_start: 0,
+                byte_end: 3,
+                replacement: "bar".to_owned(),
+            }]
+        );
+    }
+}
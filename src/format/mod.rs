@@ -0,0 +1,9 @@
+//! Parsing (and, increasingly, acting on) cargo/rustc's JSON message format.
+
+mod diagnostic;
+mod fix;
+mod render;
+
+pub use diagnostic::*;
+pub use fix::*;
+pub use render::*;
@@ -0,0 +1,192 @@
+//! Render a `Diagnostic` as an rustc-style annotated snippet, for consumers
+//! that request `--message-format=json` without `rendered` (or that build
+//! their own UI).
+
+use std::fmt::Write;
+
+use super::diagnostic::{Diagnostic, DiagnosticLevel, DiagnosticSpan};
+
+/// Controls whether `render` emits ANSI color escapes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderMode {
+    /// Plain text, no color.
+    Plain,
+    /// ANSI-colored, keyed off `DiagnosticLevel`.
+    Ansi,
+}
+
+/// Render `diagnostic` as a caret-underlined snippet, recursing into
+/// `children` as notes/help, similar to rustc's own human-readable output.
+///
+/// Only `is_primary` spans are rendered: that's the point, or one of the
+/// points, rustc considers the error to actually be at, and secondary spans
+/// usually restate information already covered by the message or a child
+/// note. Non-primary spans are never rendered, and this module offers no
+/// function that renders a single span on its own.
+pub fn render(diagnostic: &Diagnostic<'_>, mode: RenderMode) -> String {
+    let mut out = String::new();
+    render_into(diagnostic, mode, 0, &mut out);
+    out
+}
+
+fn render_into(diagnostic: &Diagnostic<'_>, mode: RenderMode, depth: usize, out: &mut String) {
+    let indent = "  ".repeat(depth);
+    let (color, reset) = match mode {
+        RenderMode::Ansi => (level_color(diagnostic.level), "\u{1b}[0m"),
+        RenderMode::Plain => ("", ""),
+    };
+
+    let _ = write!(out, "{}{}{}{}", indent, color, level_label(diagnostic.level), reset);
+    if let Some(code) = &diagnostic.code {
+        let _ = write!(out, "[{}]", code.code);
+    }
+    let _ = writeln!(out, ": {}", diagnostic.message);
+
+    for span in diagnostic.spans.iter().filter(|span| span.is_primary) {
+        render_span(span, &indent, out);
+    }
+
+    for child in &diagnostic.children {
+        render_into(child, mode, depth + 1, out);
+    }
+}
+
+fn render_span(span: &DiagnosticSpan<'_>, indent: &str, out: &mut String) {
+    let _ = writeln!(
+        out,
+        "{}  --> {}:{}:{}",
+        indent,
+        span.file_name.display(),
+        span.line_start,
+        span.column_start
+    );
+    let last_line = span.text.len().saturating_sub(1);
+    for (i, line) in span.text.iter().enumerate() {
+        let _ = writeln!(out, "{}   | {}", indent, line.text);
+        let caret_start = line.highlight_start.saturating_sub(1);
+        let caret_len = line
+            .highlight_end
+            .saturating_sub(line.highlight_start)
+            .max(1);
+        let _ = write!(
+            out,
+            "{}   | {}{}",
+            indent,
+            " ".repeat(caret_start),
+            "^".repeat(caret_len)
+        );
+        // Put the label right after the carets on the highlighted line, the
+        // way rustc places it, instead of on a trailing line of its own.
+        if i == last_line {
+            if let Some(label) = &span.label {
+                let _ = write!(out, " {}", label);
+            }
+        }
+        let _ = writeln!(out);
+    }
+}
+
+fn level_label(level: DiagnosticLevel) -> &'static str {
+    match level {
+        DiagnosticLevel::Ice => "error: internal compiler error",
+        DiagnosticLevel::Error => "error",
+        DiagnosticLevel::Warning => "warning",
+        DiagnosticLevel::Note => "note",
+        DiagnosticLevel::Help => "help",
+        #[cfg(not(feature = "strict_unstable"))]
+        DiagnosticLevel::Unknown => "unknown",
+    }
+}
+
+fn level_color(level: DiagnosticLevel) -> &'static str {
+    match level {
+        DiagnosticLevel::Ice | DiagnosticLevel::Error => "\u{1b}[1;31m",
+        DiagnosticLevel::Warning => "\u{1b}[1;33m",
+        DiagnosticLevel::Note => "\u{1b}[1;36m",
+        DiagnosticLevel::Help => "\u{1b}[1;32m",
+        #[cfg(not(feature = "strict_unstable"))]
+        DiagnosticLevel::Unknown => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::diagnostic::Diagnostic;
+
+    #[test]
+    fn renders_plain_primary_span_and_child_note() {
+        let json = r#"{
+            "message": "unused variable: `x`",
+            "code": {"code": "unused_variables", "explanation": null},
+            "level": "warning",
+            "spans": [{
+                "file_name": "src/lib.rs",
+                "byte_start": 10,
+                "byte_end": 11,
+                "line_start": 2,
+                "line_end": 2,
+                "column_start": 5,
+                "column_end": 6,
+                "is_primary": true,
+                "text": [{"text": "let x = 1;", "highlight_start": 5, "highlight_end": 6}],
+                "label": "unused variable",
+                "suggested_replacement": null,
+                "suggestion_applicability": null,
+                "expansion": null
+            }],
+            "children": [{
+                "message": "consider prefixing with an underscore: `_x`",
+                "code": null,
+                "level": "help",
+                "spans": [],
+                "children": [],
+                "rendered": null
+            }],
+            "rendered": null
+        }"#;
+        let diagnostic: Diagnostic<'_> = serde_json::from_str(json).unwrap();
+
+        let rendered = render(&diagnostic, RenderMode::Plain);
+
+        let expected = "\
+warning[unused_variables]: unused variable: `x`
+  --> src/lib.rs:2:5
+   | let x = 1;
+   |     ^ unused variable
+  help: consider prefixing with an underscore: `_x`
+";
+        assert_eq!(rendered, expected);
+    }
+
+    #[test]
+    fn renders_non_primary_spans_are_skipped() {
+        let json = r#"{
+            "message": "msg",
+            "code": null,
+            "level": "error",
+            "spans": [{
+                "file_name": "src/lib.rs",
+                "byte_start": 0,
+                "byte_end": 1,
+                "line_start": 1,
+                "line_end": 1,
+                "column_start": 1,
+                "column_end": 2,
+                "is_primary": false,
+                "text": [{"text": "x", "highlight_start": 1, "highlight_end": 2}],
+                "label": "secondary",
+                "suggested_replacement": null,
+                "suggestion_applicability": null,
+                "expansion": null
+            }],
+            "children": [],
+            "rendered": null
+        }"#;
+        let diagnostic: Diagnostic<'_> = serde_json::from_str(json).unwrap();
+
+        let rendered = render(&diagnostic, RenderMode::Plain);
+
+        assert_eq!(rendered, "error: msg\n");
+    }
+}